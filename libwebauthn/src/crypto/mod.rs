@@ -0,0 +1,65 @@
+//! Low-level cryptographic primitives used by [`crate::pin`], factored behind a
+//! [`CryptoBackend`] trait so that the PIN/UV auth protocol logic does not depend on any one
+//! crypto library. `crypto-rustcrypto` is the only backend currently wired up as
+//! [`ActiveCryptoBackend`]; `crypto-openssl` exists to incubate a future one.
+
+use super::transport::error::Error;
+
+#[cfg(feature = "crypto-openssl")]
+mod openssl;
+#[cfg(feature = "crypto-rustcrypto")]
+mod rustcrypto;
+
+// `crypto-openssl` only gates compiling the incubating `OpenSslBackend` module; that backend is
+// not yet implemented, so it is deliberately never wired up as `ActiveCryptoBackend` below.
+#[cfg(feature = "crypto-rustcrypto")]
+pub use self::rustcrypto::RustCryptoBackend as ActiveCryptoBackend;
+
+#[cfg(not(feature = "crypto-rustcrypto"))]
+compile_error!(
+    "The `crypto-rustcrypto` feature must be enabled; it is the only backend that can currently \
+     be selected as `ActiveCryptoBackend` (`crypto-openssl` exists only to incubate a future one)."
+);
+
+/// The low-level cryptographic primitives required by the PIN/UV auth protocols
+/// ([`crate::pin::PinUvAuthProtocolOne`], [`crate::pin::PinUvAuthProtocolTwo`]), selected at
+/// compile time via cargo feature. Implementing this trait lets downstream consumers swap in a
+/// FIPS-validated crypto library without forking the protocol logic itself.
+pub trait CryptoBackend {
+    /// gen_p256() → (privateKey, publicKey)
+    ///   Generates a fresh P-256 key pair, returning the private scalar and the uncompressed
+    ///   public point coordinates (x, y).
+    fn gen_p256() -> ([u8; 32], ([u8; 32], [u8; 32]));
+
+    /// ecdhe_p256(privateKey, peerX, peerY) → Z | error
+    ///   Performs a P-256 ECDH scalar multiplication of the peer's point with privateKey,
+    ///   returning the shared point's X-coordinate, Z.
+    fn ecdhe_p256(
+        private_key: &[u8; 32],
+        peer_x: &[u8; 32],
+        peer_y: &[u8; 32],
+    ) -> Result<[u8; 32], Error>;
+
+    /// AES-256-CBC encryption with no padding. `plaintext` must be a multiple of the AES block
+    /// length (16 bytes).
+    fn aes_256_cbc_encrypt_no_pad(
+        key: &[u8; 32],
+        iv: &[u8; 16],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    /// AES-256-CBC decryption with no padding. `ciphertext` must be a multiple of the AES block
+    /// length (16 bytes).
+    fn aes_256_cbc_decrypt_no_pad(
+        key: &[u8; 32],
+        iv: &[u8; 16],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32];
+
+    fn sha256(data: &[u8]) -> [u8; 32];
+
+    /// Fills an `N`-byte array with cryptographically secure random bytes.
+    fn random_bytes<const N: usize>() -> [u8; N];
+}
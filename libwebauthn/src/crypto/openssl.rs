@@ -0,0 +1,53 @@
+//! OpenSSL-backed [`CryptoBackend`] implementation, for consumers that must use a
+//! FIPS-validated OpenSSL build. Enabled by the `crypto-openssl` feature.
+//!
+//! Not yet implemented — this module exists to establish the shape downstream consumers can
+//! fill in without forking the PIN/UV auth protocol logic in [`crate::pin`].
+
+use crate::transport::error::Error;
+
+use super::CryptoBackend;
+
+pub struct OpenSslBackend;
+
+impl CryptoBackend for OpenSslBackend {
+    fn gen_p256() -> ([u8; 32], ([u8; 32], [u8; 32])) {
+        unimplemented!("crypto-openssl backend is not yet implemented")
+    }
+
+    fn ecdhe_p256(
+        _private_key: &[u8; 32],
+        _peer_x: &[u8; 32],
+        _peer_y: &[u8; 32],
+    ) -> Result<[u8; 32], Error> {
+        unimplemented!("crypto-openssl backend is not yet implemented")
+    }
+
+    fn aes_256_cbc_encrypt_no_pad(
+        _key: &[u8; 32],
+        _iv: &[u8; 16],
+        _plaintext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        unimplemented!("crypto-openssl backend is not yet implemented")
+    }
+
+    fn aes_256_cbc_decrypt_no_pad(
+        _key: &[u8; 32],
+        _iv: &[u8; 16],
+        _ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        unimplemented!("crypto-openssl backend is not yet implemented")
+    }
+
+    fn hmac_sha256(_key: &[u8], _message: &[u8]) -> [u8; 32] {
+        unimplemented!("crypto-openssl backend is not yet implemented")
+    }
+
+    fn sha256(_data: &[u8]) -> [u8; 32] {
+        unimplemented!("crypto-openssl backend is not yet implemented")
+    }
+
+    fn random_bytes<const N: usize>() -> [u8; N] {
+        unimplemented!("crypto-openssl backend is not yet implemented")
+    }
+}
@@ -0,0 +1,111 @@
+//! Default [`CryptoBackend`] implementation, built on the pure-Rust RustCrypto crates (`p256`,
+//! `aes`, `cbc`, `hmac`, `sha2`). Enabled by the `crypto-rustcrypto` feature, which is on by
+//! default.
+
+use aes::cipher::{block_padding::NoPadding, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hmac::Mac;
+use p256::{
+    ecdh::diffie_hellman,
+    elliptic_curve::sec1::FromEncodedPoint,
+    EncodedPoint, FieldBytes, NonZeroScalar, PublicKey as P256PublicKey, SecretKey,
+};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::proto::CtapError;
+use crate::transport::error::Error;
+
+use super::CryptoBackend;
+
+type Aes256CbcEncryptor = cbc::Encryptor<aes::Aes256>;
+type Aes256CbcDecryptor = cbc::Decryptor<aes::Aes256>;
+type HmacSha256 = hmac::Hmac<Sha256>;
+
+pub struct RustCryptoBackend;
+
+impl CryptoBackend for RustCryptoBackend {
+    fn gen_p256() -> ([u8; 32], ([u8; 32], [u8; 32])) {
+        let secret_key = SecretKey::random(&mut OsRng);
+        let point = EncodedPoint::from(secret_key.public_key());
+
+        let mut x = [0u8; 32];
+        x.copy_from_slice(point.x().expect("Not the identity point").as_slice());
+        let mut y = [0u8; 32];
+        y.copy_from_slice(point.y().expect("Not identity nor compressed").as_slice());
+
+        (secret_key.to_bytes().into(), (x, y))
+    }
+
+    fn ecdhe_p256(
+        private_key: &[u8; 32],
+        peer_x: &[u8; 32],
+        peer_y: &[u8; 32],
+    ) -> Result<[u8; 32], Error> {
+        let Some(scalar) =
+            Option::<NonZeroScalar>::from(NonZeroScalar::from_repr(FieldBytes::from(*private_key)))
+        else {
+            error!("Invalid P-256 private scalar");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+
+        let encoded_point =
+            EncodedPoint::from_affine_coordinates(peer_x.into(), peer_y.into(), false);
+        let Some(peer_public_key) =
+            Option::<P256PublicKey>::from(P256PublicKey::from_encoded_point(&encoded_point))
+        else {
+            error!("Failed to parse peer public key.");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+
+        let shared = diffie_hellman(&scalar, peer_public_key.as_affine());
+        Ok(*shared.raw_secret_bytes().as_ref())
+    }
+
+    fn aes_256_cbc_encrypt_no_pad(
+        key: &[u8; 32],
+        iv: &[u8; 16],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let Ok(enc) = Aes256CbcEncryptor::new_from_slices(key, iv) else {
+            error!("Invalid key for AES-256 encryption");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+        Ok(enc.encrypt_padded_vec_mut::<NoPadding>(plaintext))
+    }
+
+    fn aes_256_cbc_decrypt_no_pad(
+        key: &[u8; 32],
+        iv: &[u8; 16],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let Ok(dec) = Aes256CbcDecryptor::new_from_slices(key, iv) else {
+            error!("Invalid key for AES-256 decryption");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+        let Ok(plaintext) = dec.decrypt_padded_vec_mut::<NoPadding>(ciphertext) else {
+            error!("Unpad error while decrypting");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+        Ok(plaintext)
+    }
+
+    fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+        let mut hmac = HmacSha256::new_from_slice(key).expect("Any key size is valid");
+        hmac.update(message);
+        hmac.finalize().into_bytes().into()
+    }
+
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::default();
+        hasher.update(data);
+        hasher.finalize().into()
+    }
+
+    fn random_bytes<const N: usize>() -> [u8; N] {
+        let mut bytes = [0u8; N];
+        OsRng.fill_bytes(&mut bytes);
+        bytes
+    }
+}
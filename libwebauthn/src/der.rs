@@ -0,0 +1,187 @@
+//! A minimal DER encoder/decoder for the X.509 SubjectPublicKeyInfo structures used to exchange
+//! P-256 key-agreement keys with OpenSSL/NSS-based stacks and stored keys, which generally expect
+//! SPKI rather than COSE keys. Only the tags needed for that structure are implemented: SEQUENCE,
+//! OBJECT IDENTIFIER, and BIT STRING.
+
+use super::transport::error::Error;
+use crate::proto::CtapError;
+use tracing::error;
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_OID: u8 = 0x06;
+const TAG_BIT_STRING: u8 = 0x03;
+
+/// id-ecPublicKey, 1.2.840.10045.2.1
+const OID_EC_PUBLIC_KEY: [u8; 7] = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+/// secp256r1 (a.k.a. prime256v1), 1.2.840.10045.3.1.7
+const OID_SECP256R1: [u8; 8] = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+fn write_tlv(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    if value.len() < 0x80 {
+        out.push(value.len() as u8);
+    } else {
+        let len_bytes: Vec<u8> = value
+            .len()
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        out.push(0x80 | len_bytes.len() as u8);
+        out.extend_from_slice(&len_bytes);
+    }
+    out.extend_from_slice(value);
+}
+
+/// Serializes an uncompressed P-256 public point (X, Y) as an X.509 SubjectPublicKeyInfo DER
+/// blob for the `id-ecPublicKey`/`secp256r1` algorithm.
+pub fn spki_der_from_p256_point(x: &[u8; 32], y: &[u8; 32]) -> Vec<u8> {
+    let mut algorithm = Vec::new();
+    write_tlv(&mut algorithm, TAG_OID, &OID_EC_PUBLIC_KEY);
+    write_tlv(&mut algorithm, TAG_OID, &OID_SECP256R1);
+    let mut algorithm_seq = Vec::new();
+    write_tlv(&mut algorithm_seq, TAG_SEQUENCE, &algorithm);
+
+    // SEC1 uncompressed point encoding: 0x04 || X || Y.
+    let mut point = Vec::with_capacity(65);
+    point.push(0x04);
+    point.extend_from_slice(x);
+    point.extend_from_slice(y);
+
+    // A BIT STRING's contents are prefixed with a one-byte "number of unused bits" count; the
+    // point is always a whole number of bytes, so that count is always 0.
+    let mut bit_string_value = Vec::with_capacity(point.len() + 1);
+    bit_string_value.push(0);
+    bit_string_value.extend_from_slice(&point);
+    let mut bit_string = Vec::new();
+    write_tlv(&mut bit_string, TAG_BIT_STRING, &bit_string_value);
+
+    let mut spki = algorithm_seq;
+    spki.extend_from_slice(&bit_string);
+
+    let mut out = Vec::new();
+    write_tlv(&mut out, TAG_SEQUENCE, &spki);
+    out
+}
+
+/// Reads a single DER TLV with the given tag, returning (value, remainder).
+fn read_tlv(der: &[u8], expected_tag: u8) -> Result<(&[u8], &[u8]), Error> {
+    let Some((&tag, rest)) = der.split_first() else {
+        error!("Unexpected end of DER input");
+        return Err(Error::Ctap(CtapError::Other));
+    };
+    if tag != expected_tag {
+        error!(tag, expected_tag, "Unexpected DER tag");
+        return Err(Error::Ctap(CtapError::Other));
+    }
+    let Some((&len_byte, rest)) = rest.split_first() else {
+        error!("Unexpected end of DER input");
+        return Err(Error::Ctap(CtapError::Other));
+    };
+    let (len, rest) = if len_byte & 0x80 == 0 {
+        (len_byte as usize, rest)
+    } else {
+        let n = (len_byte & 0x7f) as usize;
+        if rest.len() < n {
+            error!("Truncated DER length");
+            return Err(Error::Ctap(CtapError::Other));
+        }
+        let (len_bytes, rest) = rest.split_at(n);
+        let len = len_bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, rest)
+    };
+    if rest.len() < len {
+        error!("Truncated DER value");
+        return Err(Error::Ctap(CtapError::Other));
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Parses an X.509 SubjectPublicKeyInfo DER blob for an `id-ecPublicKey`/`secp256r1` key back
+/// into its uncompressed point coordinates (X, Y), directly usable as the peer key in
+/// [`crate::crypto::CryptoBackend::ecdhe_p256`].
+pub fn p256_point_from_spki_der(der: &[u8]) -> Result<([u8; 32], [u8; 32]), Error> {
+    let (spki, rest) = read_tlv(der, TAG_SEQUENCE)?;
+    if !rest.is_empty() {
+        error!("Trailing bytes after SubjectPublicKeyInfo");
+        return Err(Error::Ctap(CtapError::Other));
+    }
+
+    let (algorithm, spki) = read_tlv(spki, TAG_SEQUENCE)?;
+    let (oid, algorithm) = read_tlv(algorithm, TAG_OID)?;
+    if oid != OID_EC_PUBLIC_KEY {
+        error!(?oid, "Not an id-ecPublicKey SubjectPublicKeyInfo");
+        return Err(Error::Ctap(CtapError::Other));
+    }
+    let (curve_oid, _) = read_tlv(algorithm, TAG_OID)?;
+    if curve_oid != OID_SECP256R1 {
+        error!(?curve_oid, "Not a secp256r1 key");
+        return Err(Error::Ctap(CtapError::Other));
+    }
+
+    let (bit_string, _) = read_tlv(spki, TAG_BIT_STRING)?;
+    let Some((&unused_bits, point)) = bit_string.split_first() else {
+        error!("Empty BIT STRING");
+        return Err(Error::Ctap(CtapError::Other));
+    };
+    if unused_bits != 0 || point.len() != 65 || point[0] != 0x04 {
+        error!("Expected an uncompressed 65-byte EC point");
+        return Err(Error::Ctap(CtapError::Other));
+    }
+
+    let mut x = [0u8; 32];
+    x.copy_from_slice(&point[1..33]);
+    let mut y = [0u8; 32];
+    y.copy_from_slice(&point[33..65]);
+    Ok((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_point() -> ([u8; 32], [u8; 32]) {
+        let mut x = [0u8; 32];
+        let mut y = [0u8; 32];
+        for i in 0..32 {
+            x[i] = i as u8;
+            y[i] = 0xff - i as u8;
+        }
+        (x, y)
+    }
+
+    #[test]
+    fn round_trips_through_spki_der() {
+        let (x, y) = sample_point();
+        let der = spki_der_from_p256_point(&x, &y);
+        assert_eq!(p256_point_from_spki_der(&der).unwrap(), (x, y));
+    }
+
+    #[test]
+    fn rejects_truncated_der() {
+        let (x, y) = sample_point();
+        let der = spki_der_from_p256_point(&x, &y);
+        assert!(p256_point_from_spki_der(&der[..der.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_garbage() {
+        let (x, y) = sample_point();
+        let mut der = spki_der_from_p256_point(&x, &y);
+        der.push(0);
+        assert!(p256_point_from_spki_der(&der).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_curve_oid() {
+        let (x, y) = sample_point();
+        let mut der = spki_der_from_p256_point(&x, &y);
+        // Flip a byte inside the secp256r1 OID so it no longer matches.
+        let oid_pos = der
+            .windows(OID_SECP256R1.len())
+            .position(|w| w == OID_SECP256R1)
+            .expect("secp256r1 OID must be present in a freshly encoded SPKI");
+        der[oid_pos] ^= 0xff;
+        assert!(p256_point_from_spki_der(&der).is_err());
+    }
+}
@@ -1,25 +1,18 @@
 use super::transport::error::Error;
 
-use aes::cipher::{block_padding::NoPadding, BlockDecryptMut};
 use async_trait::async_trait;
-use cbc::cipher::{BlockEncryptMut, KeyIvInit};
-use hmac::Mac;
-use p256::{
-    ecdh::EphemeralSecret, elliptic_curve::sec1::FromEncodedPoint, EncodedPoint,
-    PublicKey as P256PublicKey,
-};
-use rand::rngs::OsRng;
-use sha2::{Digest, Sha256};
 use tracing::{error, info, instrument};
-use x509_parser::nom::AsBytes;
 
+use crate::crypto::{ActiveCryptoBackend as Crypto, CryptoBackend};
 use crate::proto::{ctap2::Ctap2PinUvAuthProtocol, CtapError};
 
-type Aes256CbcEncryptor = cbc::Encryptor<aes::Aes256>;
-type Aes256CbcDecryptor = cbc::Decryptor<aes::Aes256>;
-type HmacSha256 = hmac::Hmac<Sha256>;
-
 pub struct PinUvAuthToken {
+    /// The raw, decrypted pinUvAuthToken. Used to MAC (via
+    /// [`PinUvAuthProtocol::authenticate`]) the `pinUvAuthParam` of subsequent requests.
+    pub token: Vec<u8>,
+    /// The `permissions` bitmask (see the [`permissions`] module) this token was granted for, or
+    /// `0` if it was obtained via the legacy `getPinToken` flow, which carries no permissions.
+    pub permissions: u8,
     pub rpid: Option<String>,
     pub user_verified: bool,
     pub user_present: bool,
@@ -28,6 +21,8 @@ pub struct PinUvAuthToken {
 impl Default for PinUvAuthToken {
     fn default() -> Self {
         Self {
+            token: Vec::new(),
+            permissions: 0,
             rpid: None,
             user_verified: false,
             user_present: false,
@@ -35,6 +30,26 @@ impl Default for PinUvAuthToken {
     }
 }
 
+/// Permission bits for `getPinUvAuthTokenUsingPinWithPermissions`, as defined in CTAP 2.1 §6.5.5.7.
+pub mod permissions {
+    pub const MAKE_CREDENTIAL: u8 = 0x01;
+    pub const GET_ASSERTION: u8 = 0x02;
+    pub const CREDENTIAL_MANAGEMENT: u8 = 0x04;
+    pub const BIO_ENROLLMENT: u8 = 0x08;
+    pub const LARGE_BLOB_WRITE: u8 = 0x10;
+    pub const AUTHENTICATOR_CONFIGURATION: u8 = 0x20;
+}
+
+/// Derives the (user_present, user_verified) flags a `pinUvAuthToken` carries for the given
+/// granted `permissions`, per CTAP 2.1 §6.5.5.7.2: obtaining a token via PIN always verifies the
+/// user, and additionally counts as testing user presence when the token is permitted to be used
+/// for `mc` (`MAKE_CREDENTIAL`) or `ga` (`GET_ASSERTION`).
+fn token_flags_from_permissions(permissions: u8) -> (bool, bool) {
+    let user_present = permissions & (self::permissions::MAKE_CREDENTIAL | self::permissions::GET_ASSERTION) != 0;
+    let user_verified = true;
+    (user_present, user_verified)
+}
+
 #[async_trait]
 pub trait PinProvider {
     async fn provide_pin(&self, attempts_left: Option<u32>) -> Option<String>;
@@ -87,17 +102,82 @@ pub trait PinUvAuthProtocol {
     // authenticate(key, message) → signature
     //   Computes a MAC of the given message.
     fn authenticate(&self, key: &[u8], message: &[u8]) -> Vec<u8>;
+
+    /// verify(key, message, signature) → success | error
+    ///   Verifies that the signature is a valid MAC for the given message under key, recomputing
+    ///   it via authenticate() and comparing in constant time so that the timing of the
+    ///   comparison cannot be used to learn how many leading bytes of signature are correct.
+    fn verify(&self, key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        let mac = self.authenticate(key, message);
+        // An empty MAC (what authenticate() returns for a mis-sized key) never verifies.
+        !mac.is_empty() && is_equal(&mac, signature)
+    }
+}
+
+/// Constant-time byte-slice equality. Unlike `==`, this does not short-circuit on the first
+/// differing byte, so it does not leak timing information about where two MACs diverge.
+fn is_equal(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
+/// Parses a peerCoseKey as specified for getPublicKey and performs the scalar-multiplication of
+/// the peer's point, Y, with the local private key agreement key, returning Z, the x-coordinate
+/// of the shared point.
+/// Shared by all PIN/UV auth protocols, which differ only in how they turn Z into a sharedSecret.
+fn ecdh_p256(private_key: &[u8; 32], peer_public_key: &cosey::PublicKey) -> Result<[u8; 32], Error> {
+    // Parse peerCoseKey as specified for getPublicKey, below, and produce a P-256 point, Y.
+    // If unsuccessful, or if the resulting point is not on the curve, return error.
+    let cosey::PublicKey::EcdhEsHkdf256Key(peer_public_key) = peer_public_key else {
+        error!(
+            ?peer_public_key,
+            "Unsupported peerCoseKey format. Only EcdhEsHkdf256Key is supported."
+        );
+        return Err(Error::Ctap(CtapError::Other));
+    };
+    let (Ok(peer_x), Ok(peer_y)) = (
+        <[u8; 32]>::try_from(peer_public_key.x.as_slice()),
+        <[u8; 32]>::try_from(peer_public_key.y.as_slice()),
+    ) else {
+        error!("peerCoseKey coordinates are not 32 bytes long.");
+        return Err(Error::Ctap(CtapError::Other));
+    };
+
+    // Calculate xY, the shared point. (I.e. the scalar-multiplication of the peer’s point, Y, with the
+    // local private key agreement key.)
+    Crypto::ecdhe_p256(private_key, &peer_x, &peer_y)
+}
+
+/// As [`ecdh_p256`], but for a peer public key received as an X.509 SubjectPublicKeyInfo DER
+/// blob rather than a COSE key, e.g. from an OpenSSL/NSS-based stack or a stored key.
+fn ecdh_p256_spki_der(private_key: &[u8; 32], peer_public_key_der: &[u8]) -> Result<[u8; 32], Error> {
+    let (peer_x, peer_y) = crate::der::p256_point_from_spki_der(peer_public_key_der)?;
+    Crypto::ecdhe_p256(private_key, &peer_x, &peer_y)
+}
+
+/// getPublicKey(), shared by all PIN/UV auth protocols.
+fn get_public_key_p256(public_key: &([u8; 32], [u8; 32])) -> cosey::PublicKey {
+    let (x, y) = public_key;
+    cosey::PublicKey::P256Key(cosey::P256PublicKey {
+        x: heapless::Vec::from_slice(x).unwrap().into(),
+        y: heapless::Vec::from_slice(y).unwrap().into(),
+    })
 }
 
 pub struct PinUvAuthProtocolOne {
-    private_key: EphemeralSecret,
-    public_key: P256PublicKey,
+    private_key: [u8; 32],
+    public_key: ([u8; 32], [u8; 32]),
 }
 
 impl PinUvAuthProtocolOne {
     pub fn new() -> Self {
-        let private_key = EphemeralSecret::random(&mut OsRng);
-        let public_key = private_key.public_key();
+        let (private_key, public_key) = Crypto::gen_p256();
         Self {
             private_key,
             public_key,
@@ -106,50 +186,33 @@ impl PinUvAuthProtocolOne {
 
     /// ecdh(peerCoseKey) → sharedSecret | error
     fn ecdh(&self, peer_public_key: &cosey::PublicKey) -> Result<Vec<u8>, Error> {
-        // Parse peerCoseKey as specified for getPublicKey, below, and produce a P-256 point, Y.
-        // If unsuccessful, or if the resulting point is not on the curve, return error.
-        let cosey::PublicKey::EcdhEsHkdf256Key(peer_public_key) = peer_public_key else {
-            error!(?peer_public_key, "Unsupported peerCoseKey format. Only EcdhEsHkdf256Key is supported.");
-            return Err(Error::Ctap(CtapError::Other));
-        };
-        let encoded_point = EncodedPoint::from_affine_coordinates(
-            peer_public_key.x.as_bytes().into(),
-            peer_public_key.y.as_bytes().into(),
-            false,
-        );
-        let Some(peer_public_key) = P256PublicKey::from_encoded_point(&encoded_point).into() else {
-            error!("Failed to parse public key.");
-            return Err(Error::Ctap(CtapError::Other));
-        };
-
-        // Calculate xY, the shared point. (I.e. the scalar-multiplication of the peer’s point, Y, with the
-        // local private key agreement key.)
-        let shared = self.private_key.diffie_hellman(&peer_public_key);
-
+        let z = ecdh_p256(&self.private_key, peer_public_key)?;
         // Return kdf(Z).
-        Ok(self.kdf(shared.as_bytes().as_bytes()))
+        Ok(self.kdf(&z))
+    }
+
+    /// As [`Self::ecdh`], but for a peer public key received as an X.509 SubjectPublicKeyInfo
+    /// DER blob (e.g. from an OpenSSL/NSS-based stack) rather than a COSE key.
+    pub fn ecdh_using_peer_spki_der(&self, peer_public_key_der: &[u8]) -> Result<Vec<u8>, Error> {
+        let z = ecdh_p256_spki_der(&self.private_key, peer_public_key_der)?;
+        Ok(self.kdf(&z))
     }
 
     /// kdf(Z) → sharedSecret
     fn kdf(&self, bytes: &[u8]) -> Vec<u8> {
-        let mut hasher = Sha256::default();
-        hasher.update(bytes);
-        hasher.finalize().to_vec()
+        Crypto::sha256(bytes).to_vec()
     }
 
     /// getPublicKey()
     fn get_public_key(&self) -> cosey::PublicKey {
-        let point = EncodedPoint::from(self.public_key);
-        let x: heapless::Vec<u8, 32> =
-            heapless::Vec::from_slice(point.x().expect("Not the identity point").as_bytes())
-                .unwrap();
-        let y: heapless::Vec<u8, 32> =
-            heapless::Vec::from_slice(point.y().expect("Not identity nor compressed").as_bytes())
-                .unwrap();
-        cosey::PublicKey::P256Key(cosey::P256PublicKey {
-            x: x.into(),
-            y: y.into(),
-        })
+        get_public_key_p256(&self.public_key)
+    }
+
+    /// Serializes the ephemeral public key as an X.509 SubjectPublicKeyInfo DER blob, for
+    /// interop with OpenSSL/NSS-based stacks that expect SPKI rather than a COSE key.
+    pub fn get_public_key_spki_der(&self) -> Vec<u8> {
+        let (x, y) = &self.public_key;
+        crate::der::spki_der_from_p256_point(x, y)
     }
 }
 
@@ -173,17 +236,16 @@ impl PinUvAuthProtocol for PinUvAuthProtocolOne {
     fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
         // Return the AES-256-CBC encryption of demPlaintext using an all-zero IV.
         // (No padding is performed as the size of demPlaintext is required to be a multiple of the AES block length.)
-        let iv: &[u8] = &[0; 16];
-        let Ok(enc) = Aes256CbcEncryptor::new_from_slices(key, iv) else {
-            error!(?key, "Invalid key for AES-256 encryption");
+        let Ok(key) = key.try_into() else {
+            error!(?key, "Invalid key length for AES-256 encryption");
             return Err(Error::Ctap(CtapError::Other));
         };
-        Ok(enc.encrypt_padded_vec_mut::<NoPadding>(plaintext))
+        Crypto::aes_256_cbc_encrypt_no_pad(key, &[0; 16], plaintext)
     }
 
     fn authenticate(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
         // Return the first 16 bytes of the result of computing HMAC-SHA-256 with the given key and message.
-        let hmac = hmac_sha256(key, message);
+        let hmac = Crypto::hmac_sha256(key, message);
         Vec::from(&hmac[..16])
     }
 
@@ -197,30 +259,452 @@ impl PinUvAuthProtocol for PinUvAuthProtocolOne {
             );
             return Err(Error::Ctap(CtapError::Other));
         }
+        let Ok(key) = key.try_into() else {
+            error!(?key, "Invalid key length for AES-256 decryption");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+        Crypto::aes_256_cbc_decrypt_no_pad(key, &[0; 16], ciphertext)
+    }
+}
+
+/// PIN/UV Auth Protocol Two, as used by CTAP 2.1 authenticators. Unlike protocol one, the shared
+/// secret is split into separate HMAC and AES keys, encryption uses a random IV per message
+/// instead of an all-zero one, and MACs are not truncated.
+pub struct PinUvAuthProtocolTwo {
+    private_key: [u8; 32],
+    public_key: ([u8; 32], [u8; 32]),
+}
+
+impl Default for PinUvAuthProtocolTwo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PinUvAuthProtocolTwo {
+    pub fn new() -> Self {
+        let (private_key, public_key) = Crypto::gen_p256();
+        Self {
+            private_key,
+            public_key,
+        }
+    }
+
+    /// ecdh(peerCoseKey) → sharedSecret | error
+    fn ecdh(&self, peer_public_key: &cosey::PublicKey) -> Result<Vec<u8>, Error> {
+        let z = ecdh_p256(&self.private_key, peer_public_key)?;
+        // Return kdf(Z).
+        Ok(self.kdf(&z))
+    }
+
+    /// As [`Self::ecdh`], but for a peer public key received as an X.509 SubjectPublicKeyInfo
+    /// DER blob (e.g. from an OpenSSL/NSS-based stack) rather than a COSE key.
+    pub fn ecdh_using_peer_spki_der(&self, peer_public_key_der: &[u8]) -> Result<Vec<u8>, Error> {
+        let z = ecdh_p256_spki_der(&self.private_key, peer_public_key_der)?;
+        Ok(self.kdf(&z))
+    }
+
+    /// kdf(Z) → sharedSecret
+    ///
+    /// Unlike protocol one, this returns HMAC-key(32) || AES-key(32), derived from Z via
+    /// HKDF-SHA-256 with an all-zero salt, run once per key with a distinct `info` string.
+    fn kdf(&self, z: &[u8]) -> Vec<u8> {
+        let salt = [0u8; 32];
+        let hkdf = hkdf::Hkdf::<sha2::Sha256>::new(Some(&salt), z);
+
+        let mut hmac_key = [0u8; 32];
+        hkdf.expand(b"CTAP2 HMAC key", &mut hmac_key)
+            .expect("32 is a valid HKDF-SHA-256 output length");
+
+        let mut aes_key = [0u8; 32];
+        hkdf.expand(b"CTAP2 AES key", &mut aes_key)
+            .expect("32 is a valid HKDF-SHA-256 output length");
+
+        let mut shared_secret = Vec::with_capacity(64);
+        shared_secret.extend_from_slice(&hmac_key);
+        shared_secret.extend_from_slice(&aes_key);
+        shared_secret
+    }
+
+    /// getPublicKey()
+    fn get_public_key(&self) -> cosey::PublicKey {
+        get_public_key_p256(&self.public_key)
+    }
+
+    /// Serializes the ephemeral public key as an X.509 SubjectPublicKeyInfo DER blob, for
+    /// interop with OpenSSL/NSS-based stacks that expect SPKI rather than a COSE key.
+    pub fn get_public_key_spki_der(&self) -> Vec<u8> {
+        let (x, y) = &self.public_key;
+        crate::der::spki_der_from_p256_point(x, y)
+    }
+}
+
+impl PinUvAuthProtocol for PinUvAuthProtocolTwo {
+    fn version(&self) -> Ctap2PinUvAuthProtocol {
+        Ctap2PinUvAuthProtocol::Two
+    }
+
+    #[instrument(skip_all)]
+    fn encapsulate(
+        &self,
+        peer_public_key: &cosey::PublicKey,
+    ) -> Result<(cosey::PublicKey, Vec<u8>), Error> {
+        // Let sharedSecret be the result of calling ecdh(peerCoseKey). Return any resulting error.
+        let shared_secret = self.ecdh(peer_public_key)?;
+
+        // Return(getPublicKey(), sharedSecret)
+        Ok((self.get_public_key(), shared_secret))
+    }
+
+    fn encrypt(&self, key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        // Generate a random 16-byte IV and AES-256-CBC encrypt using the last 32 bytes of key
+        // (the AES key) as the key. Return IV || ciphertext.
+        let Some(aes_key) = key.len().checked_sub(32).and_then(|at| key[at..].try_into().ok())
+        else {
+            error!(?key, "Invalid key length for AES-256 encryption");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+        let iv: [u8; 16] = Crypto::random_bytes();
+
+        let ciphertext = Crypto::aes_256_cbc_encrypt_no_pad(&aes_key, &iv, plaintext)?;
+
+        let mut result = Vec::with_capacity(iv.len() + ciphertext.len());
+        result.extend_from_slice(&iv);
+        result.extend_from_slice(&ciphertext);
+        Ok(result)
+    }
 
-        let iv: &[u8] = &[0; 16];
-        let Ok(dec) = Aes256CbcDecryptor::new_from_slices(key, iv) else {
-            error!(?key, "Invalid key for AES-256 decryption");
+    fn authenticate(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        // Return the full result of computing HMAC-SHA-256 with the first 32 bytes of key
+        // (the HMAC key) and message.
+        let Some(hmac_key) = key.get(..32) else {
+            error!(?key, "Invalid key length for HMAC-SHA-256");
+            // A mis-sized key can never produce a valid MAC; returning one that cannot possibly
+            // match a real signature is safer for a caller than panicking.
+            return Vec::new();
+        };
+        Crypto::hmac_sha256(hmac_key, message).to_vec()
+    }
+
+    fn decrypt(&self, key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        // The leading 16 bytes of ciphertext are the IV; the remainder must be a multiple of the
+        // AES block length (an empty remainder decrypts to an empty plaintext).
+        if ciphertext.len() < 16 || !(ciphertext.len() - 16).is_multiple_of(16) {
+            error!(
+                ?ciphertext,
+                "Ciphertext length is too short or not IV + a multiple of AES block length"
+            );
             return Err(Error::Ctap(CtapError::Other));
+        }
+        let (iv, ciphertext) = ciphertext.split_at(16);
+        let Ok(iv) = iv.try_into() else {
+            unreachable!("split_at(16) guarantees a 16-byte IV");
         };
-        let Ok(plaintext) = dec.decrypt_padded_vec_mut::<NoPadding>(ciphertext) else {
-            error!("Unpad error while decrypting");
+        let Some(aes_key) = key.len().checked_sub(32).and_then(|at| key[at..].try_into().ok())
+        else {
+            error!(?key, "Invalid key length for AES-256 decryption");
             return Err(Error::Ctap(CtapError::Other));
         };
-        Ok(plaintext)
+        Crypto::aes_256_cbc_decrypt_no_pad(&aes_key, iv, ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod protocol_two_tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt_roundtrip() {
+        let protocol = PinUvAuthProtocolTwo::new();
+        let key = protocol.kdf(b"any 32+ bytes of ECDH output, not actually validated by kdf()");
+        let plaintext = b"0123456789abcdef";
+
+        let ciphertext = protocol.encrypt(&key, plaintext).unwrap();
+        assert_eq!(protocol.decrypt(&key, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn encrypt_rejects_undersized_key() {
+        let protocol = PinUvAuthProtocolTwo::new();
+        assert!(protocol.encrypt(&[0u8; 16], b"0123456789abcdef").is_err());
+    }
+
+    #[test]
+    fn decrypt_rejects_undersized_key() {
+        let protocol = PinUvAuthProtocolTwo::new();
+        assert!(protocol.decrypt(&[0u8; 16], &[0u8; 32]).is_err());
+    }
+
+    #[test]
+    fn authenticate_does_not_panic_on_undersized_key() {
+        let protocol = PinUvAuthProtocolTwo::new();
+        assert_eq!(protocol.authenticate(&[0u8; 16], b"message"), Vec::<u8>::new());
+    }
+}
+
+#[cfg(test)]
+mod verify_tests {
+    use super::*;
+
+    #[test]
+    fn protocol_one_verify_accepts_genuine_mac() {
+        let protocol = PinUvAuthProtocolOne::new();
+        let key = [0x42u8; 32];
+        let message = b"pinUvAuthParam input";
+
+        let signature = protocol.authenticate(&key, message);
+        assert!(protocol.verify(&key, message, &signature));
+    }
+
+    #[test]
+    fn protocol_one_verify_rejects_flipped_byte() {
+        let protocol = PinUvAuthProtocolOne::new();
+        let key = [0x42u8; 32];
+        let message = b"pinUvAuthParam input";
+        let signature = protocol.authenticate(&key, message);
+
+        for offset in [0, 1, signature.len() / 2, signature.len() - 1] {
+            let mut corrupted = signature.clone();
+            corrupted[offset] ^= 0xff;
+            assert!(
+                !protocol.verify(&key, message, &corrupted),
+                "verify() should reject a signature with byte {offset} flipped"
+            );
+        }
+    }
+
+    #[test]
+    fn protocol_one_verify_rejects_wrong_length_signature() {
+        let protocol = PinUvAuthProtocolOne::new();
+        let key = [0x42u8; 32];
+        let message = b"pinUvAuthParam input";
+        let signature = protocol.authenticate(&key, message);
+
+        assert!(!protocol.verify(&key, message, &signature[..signature.len() - 1]));
+        assert!(!protocol.verify(&key, message, b""));
+    }
+
+    #[test]
+    fn protocol_two_verify_accepts_genuine_mac() {
+        let protocol = PinUvAuthProtocolTwo::new();
+        let key = [0x42u8; 32];
+        let message = b"pinUvAuthParam input";
+
+        let signature = protocol.authenticate(&key, message);
+        assert!(protocol.verify(&key, message, &signature));
+    }
+
+    #[test]
+    fn protocol_two_verify_rejects_flipped_byte() {
+        let protocol = PinUvAuthProtocolTwo::new();
+        let key = [0x42u8; 32];
+        let message = b"pinUvAuthParam input";
+        let signature = protocol.authenticate(&key, message);
+
+        for offset in [0, 1, signature.len() / 2, signature.len() - 1] {
+            let mut corrupted = signature.clone();
+            corrupted[offset] ^= 0xff;
+            assert!(
+                !protocol.verify(&key, message, &corrupted),
+                "verify() should reject a signature with byte {offset} flipped"
+            );
+        }
+    }
+
+    #[test]
+    fn protocol_two_verify_rejects_wrong_length_signature() {
+        let protocol = PinUvAuthProtocolTwo::new();
+        let key = [0x42u8; 32];
+        let message = b"pinUvAuthParam input";
+        let signature = protocol.authenticate(&key, message);
+
+        assert!(!protocol.verify(&key, message, &signature[..signature.len() - 1]));
+        assert!(!protocol.verify(&key, message, b""));
     }
 }
 
 /// hash(pin) -> LEFT(SHA-256(pin), 16)
 pub fn pin_hash(pin: &[u8]) -> Vec<u8> {
-    let mut hasher = Sha256::default();
-    hasher.update(pin);
-    let hashed = hasher.finalize().to_vec();
-    Vec::from(&hashed[..16])
+    Vec::from(&Crypto::sha256(pin)[..16])
 }
 
 pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
-    let mut hmac = HmacSha256::new_from_slice(key).expect("Any key size is valid");
-    hmac.update(message);
-    hmac.finalize().into_bytes().to_vec()
-}
\ No newline at end of file
+    Crypto::hmac_sha256(key, message).to_vec()
+}
+
+/// The authenticator-facing half of the `getPinUvAuthToken` flows: sending the `authenticatorClientPIN`
+/// subcommands and returning their raw responses. Implemented by whatever already knows how to
+/// exchange CTAP2 commands with the connected authenticator.
+#[async_trait]
+pub trait Ctap2GetPinUvAuthTokenTransport {
+    /// getKeyAgreement: fetches the authenticator's ephemeral key-agreement public key.
+    async fn get_key_agreement(&mut self) -> Result<cosey::PublicKey, Error>;
+
+    /// getPinToken (legacy, pre-CTAP 2.1): exchanges `pinHashEnc` for an encrypted pinUvAuthToken.
+    async fn get_pin_token(
+        &mut self,
+        protocol: Ctap2PinUvAuthProtocol,
+        platform_key_agreement_key: &cosey::PublicKey,
+        pin_hash_enc: &[u8],
+    ) -> Result<Vec<u8>, Error>;
+
+    /// getPinUvAuthTokenUsingPinWithPermissions: as above, but scoped to `permissions` and,
+    /// optionally, a single `rpId`.
+    async fn get_pin_uv_auth_token_using_pin_with_permissions(
+        &mut self,
+        protocol: Ctap2PinUvAuthProtocol,
+        platform_key_agreement_key: &cosey::PublicKey,
+        pin_hash_enc: &[u8],
+        permissions: u8,
+        rp_id: Option<&str>,
+    ) -> Result<Vec<u8>, Error>;
+}
+
+/// getPinToken: the legacy (pre-CTAP 2.1) flow for obtaining a `PinUvAuthToken` from a PIN.
+/// Authenticators that support CTAP 2.1 should instead be asked for a token via
+/// [`get_pin_uv_auth_token_using_pin_with_permissions`], which scopes the token to the
+/// permissions actually needed.
+///
+/// `attempts_left` should be seeded from a prior `authenticatorGetPinRetries` call, if available;
+/// it is decremented locally on each `PinInvalid` response and surfaced to `pin_provider` so the
+/// caller can warn the user before the PIN is blocked.
+#[instrument(skip(channel, protocol, pin_provider))]
+pub async fn get_pin_token<C: Ctap2GetPinUvAuthTokenTransport + Send>(
+    channel: &mut C,
+    protocol: &dyn PinUvAuthProtocol,
+    pin_provider: &dyn PinProvider,
+    mut attempts_left: Option<u32>,
+) -> Result<PinUvAuthToken, Error> {
+    let authenticator_key_agreement_key = channel.get_key_agreement().await?;
+    let (platform_key_agreement_key, shared_secret) =
+        protocol.encapsulate(&authenticator_key_agreement_key)?;
+
+    loop {
+        let Some(pin) = pin_provider.provide_pin(attempts_left).await else {
+            error!("PIN provider declined to provide a PIN");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+        let pin_hash_enc = protocol.encrypt(&shared_secret, &pin_hash(pin.as_bytes()))?;
+
+        match channel
+            .get_pin_token(protocol.version(), &platform_key_agreement_key, &pin_hash_enc)
+            .await
+        {
+            Ok(encrypted_token) => {
+                let token = protocol.decrypt(&shared_secret, &encrypted_token)?;
+                // The legacy flow carries no permissions, and per CTAP 2.1 §6.5.5.7.2 always
+                // verifies but never tests user presence.
+                return Ok(PinUvAuthToken {
+                    token,
+                    permissions: 0,
+                    rpid: None,
+                    user_verified: true,
+                    user_present: false,
+                });
+            }
+            Err(Error::Ctap(CtapError::PinInvalid)) => {
+                attempts_left = attempts_left.map(|n| n.saturating_sub(1));
+                info!(?attempts_left, "PIN was incorrect, retrying");
+                continue;
+            }
+            Err(err @ Error::Ctap(CtapError::PinAuthBlocked | CtapError::PinBlocked)) => {
+                error!(?err, "PIN is blocked; a power cycle or authenticator reset is required");
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// getPinUvAuthTokenUsingPinWithPermissions: the CTAP 2.1 flow for obtaining a `PinUvAuthToken`
+/// from a PIN, scoped to `permissions` (see the [`permissions`] module) and, optionally, a
+/// single `rp_id`.
+///
+/// `attempts_left` should be seeded from a prior `authenticatorGetPinRetries` call, if available;
+/// it is decremented locally on each `PinInvalid` response and surfaced to `pin_provider` so the
+/// caller can warn the user before the PIN is blocked.
+#[instrument(skip(channel, protocol, pin_provider))]
+pub async fn get_pin_uv_auth_token_using_pin_with_permissions<
+    C: Ctap2GetPinUvAuthTokenTransport + Send,
+>(
+    channel: &mut C,
+    protocol: &dyn PinUvAuthProtocol,
+    pin_provider: &dyn PinProvider,
+    permissions: u8,
+    rp_id: Option<&str>,
+    mut attempts_left: Option<u32>,
+) -> Result<PinUvAuthToken, Error> {
+    let authenticator_key_agreement_key = channel.get_key_agreement().await?;
+    let (platform_key_agreement_key, shared_secret) =
+        protocol.encapsulate(&authenticator_key_agreement_key)?;
+
+    loop {
+        let Some(pin) = pin_provider.provide_pin(attempts_left).await else {
+            error!("PIN provider declined to provide a PIN");
+            return Err(Error::Ctap(CtapError::Other));
+        };
+        let pin_hash_enc = protocol.encrypt(&shared_secret, &pin_hash(pin.as_bytes()))?;
+
+        match channel
+            .get_pin_uv_auth_token_using_pin_with_permissions(
+                protocol.version(),
+                &platform_key_agreement_key,
+                &pin_hash_enc,
+                permissions,
+                rp_id,
+            )
+            .await
+        {
+            Ok(encrypted_token) => {
+                let token = protocol.decrypt(&shared_secret, &encrypted_token)?;
+                let (user_present, user_verified) = token_flags_from_permissions(permissions);
+                return Ok(PinUvAuthToken {
+                    token,
+                    permissions,
+                    rpid: rp_id.map(str::to_owned),
+                    user_verified,
+                    user_present,
+                });
+            }
+            Err(Error::Ctap(CtapError::PinInvalid)) => {
+                attempts_left = attempts_left.map(|n| n.saturating_sub(1));
+                info!(?attempts_left, "PIN was incorrect, retrying");
+                continue;
+            }
+            Err(err @ Error::Ctap(CtapError::PinAuthBlocked | CtapError::PinBlocked)) => {
+                error!(?err, "PIN is blocked; a power cycle or authenticator reset is required");
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod permission_flag_tests {
+    use super::*;
+
+    #[test]
+    fn make_credential_implies_user_present() {
+        assert_eq!(
+            token_flags_from_permissions(permissions::MAKE_CREDENTIAL),
+            (true, true)
+        );
+    }
+
+    #[test]
+    fn get_assertion_implies_user_present() {
+        assert_eq!(
+            token_flags_from_permissions(permissions::GET_ASSERTION),
+            (true, true)
+        );
+    }
+
+    #[test]
+    fn credential_management_alone_does_not_imply_user_present() {
+        assert_eq!(
+            token_flags_from_permissions(permissions::CREDENTIAL_MANAGEMENT),
+            (false, true)
+        );
+    }
+}